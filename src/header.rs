@@ -1,7 +1,7 @@
 
 use nom::IResult;
 use std::collections::HashMap;
-use std::io::Result;
+use std::io::{Error, ErrorKind, Result};
 
 /// Representation of a Numpy type
 #[derive(PartialEq, Eq, Debug)]
@@ -9,6 +9,9 @@ pub struct DType {
     /// Numpy type string. First character is `'>'` for big endian, `'<'` for little endian.
     ///
     /// Examples: `>i4`, `<u8`, `>f8`. The number corresponds to the number of bytes.
+    ///
+    /// Unused (and left empty) when `nested` is `Some`, since a nested record has no dtype
+    /// string of its own.
     pub ty: String,
 
     /// Shape of a type.
@@ -16,6 +19,120 @@ pub struct DType {
     /// Scalar has zero entries. Otherwise, number of entries == number of dimensions and each
     /// entry specifies size in the respective dimension.
     pub shape: Vec<u64>,
+
+    /// If this field is itself a structured record rather than a plain element type, its
+    /// nested field list. Numpy allows record dtypes to nest arbitrarily deep.
+    pub nested: Option<Box<RecordDType>>,
+}
+
+impl DType {
+    /// The part of a field tuple that describes its type: either the quoted dtype string, or
+    /// the bracketed field list of a nested record.
+    fn type_repr(&self) -> String {
+        match self.nested {
+            Some(ref record) => record.descr(),
+            None => format!("'{}'", self.ty),
+        }
+    }
+
+    /// Parse `ty` into its byte order, type category and element size in bytes.
+    ///
+    /// Returns an error if `nested` is set (a nested record has no dtype string of its own) or
+    /// if `ty` doesn't match a recognized numpy type-string format.
+    pub fn type_descr(&self) -> Result<TypeDescr> {
+        if self.nested.is_some() {
+            return Err(malformed_descr("nested record fields have no dtype string to parse"));
+        }
+        parse_type_str(&self.ty)
+    }
+}
+
+/// Byte order encoded in a numpy dtype string.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Endianness {
+    Little,
+    Big,
+    /// Native byte order (`=`), or no byte order at all for single-byte types (`|`).
+    NotApplicable,
+}
+
+/// Type category decoded from a numpy dtype string.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum TypeKind {
+    Int,
+    Uint,
+    Float,
+    /// A pair of floats of `component_size` bytes each (e.g. `c8` is a pair of 4-byte floats).
+    Complex { component_size: u64 },
+    Bool,
+    /// Fixed-width byte string (`S`).
+    Bytes,
+    /// Fixed-width unicode string (`U`), stored as 4 bytes per character.
+    Unicode,
+    /// Raw fixed-width bytes (`V`).
+    Void,
+    /// Datetime (`M8`), with its bracketed unit (e.g. `ns`, `s`, `D`).
+    DateTime { unit: String },
+    /// Timedelta (`m8`), with its bracketed unit.
+    TimeDelta { unit: String },
+}
+
+/// A numpy dtype string (e.g. `<i4`, `|S10`, `<M8[ns]`), decoded into its structural parts.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TypeDescr {
+    pub endianness: Endianness,
+    pub kind: TypeKind,
+    /// Size of one element, in bytes.
+    pub size: u64,
+}
+
+fn parse_type_str(ty: &str) -> Result<TypeDescr> {
+    if ty.is_empty() {
+        return Err(malformed_descr("empty dtype string"));
+    }
+
+    let (endianness, rest) = match ty.as_bytes()[0] {
+        b'<' => (Endianness::Little, &ty[1..]),
+        b'>' => (Endianness::Big, &ty[1..]),
+        b'=' | b'|' => (Endianness::NotApplicable, &ty[1..]),
+        _ => (Endianness::NotApplicable, ty),
+    };
+
+    if let Some(unit_part) = rest.strip_prefix("M8") {
+        return Ok(TypeDescr { endianness, kind: TypeKind::DateTime { unit: parse_unit(unit_part) }, size: 8 });
+    }
+    if let Some(unit_part) = rest.strip_prefix("m8") {
+        return Ok(TypeDescr { endianness, kind: TypeKind::TimeDelta { unit: parse_unit(unit_part) }, size: 8 });
+    }
+
+    let mut chars = rest.chars();
+    let code = chars.next()
+        .ok_or_else(|| malformed_descr(&format!("dtype string is missing a type code: {:?}", ty)))?;
+    let digits = chars.as_str();
+    let count = if digits.is_empty() {
+        0
+    } else {
+        digits.parse::<u64>().map_err(|_| malformed_descr(&format!("invalid size in dtype string: {:?}", ty)))?
+    };
+
+    let (kind, size) = match code {
+        'i' => (TypeKind::Int, count),
+        'u' => (TypeKind::Uint, count),
+        'f' => (TypeKind::Float, count),
+        'c' => (TypeKind::Complex { component_size: count / 2 }, count),
+        'b' => (TypeKind::Bool, 1),
+        'S' => (TypeKind::Bytes, count),
+        'V' => (TypeKind::Void, count),
+        'U' => (TypeKind::Unicode, count * 4),
+        _ => return Err(malformed_descr(&format!("unrecognized dtype code in {:?}", ty))),
+    };
+
+    Ok(TypeDescr { endianness, kind, size })
+}
+
+/// Strip the optional `[unit]` suffix following `M8`/`m8`, e.g. `"[ns]"` -> `"ns"`.
+fn parse_unit(unit_part: &str) -> String {
+    unit_part.trim_start_matches('[').trim_end_matches(']').to_string()
 }
 
 /// To avoid exporting the `to_value` function, it is on a separate trait.
@@ -25,15 +142,19 @@ pub trait DTypeToValue {
 
 impl DTypeToValue for DType {
     fn to_value(&self, name: &str) -> Value {
+        let ty = match self.nested {
+            Some(ref record) => record.to_value(),
+            None => Value::String(self.ty.clone()),
+        };
         if self.shape.is_empty() { // scalar
             Value::List(vec![
                 Value::String(name.into()),
-                Value::String(self.ty.clone()),
+                ty,
             ])
         } else {
             Value::List(vec![
                 Value::String(name.into()),
-                Value::String(self.ty.clone()),
+                ty,
                 Value::List(self.shape.iter().map(|&n| Value::Integer(n as i64)).collect::<Vec<_>>()),
             ])
         }
@@ -59,14 +180,24 @@ impl RecordDType {
                 fields.iter()
                     .map(|&(ref id, ref t)|
                         if t.shape.len() == 0 {
-                            format!("('{}', '{}'), ", id, t.ty)
+                            format!("('{}', {}), ", id, t.type_repr())
                         } else {
                             let shape_str = t.shape.iter().fold(String::new(), |o,n| o + &format!("{},", n));
-                            format!("('{}', '{}', ({})), ", id, t.ty, shape_str)
+                            format!("('{}', {}, ({})), ", id, t.type_repr(), shape_str)
                         }
                     )
                     .fold("[".to_string(), |o, n| o + &n) + "]",
-            Simple(ref dtype) => format!("'{}'", dtype.ty),
+            Simple(ref dtype) => dtype.type_repr(),
+        }
+    }
+
+    /// Description AST of this record dtype, as used for a field nested inside another record.
+    fn to_value(&self) -> Value {
+        use RecordDType::*;
+        match *self {
+            Simple(ref dtype) => Value::String(dtype.ty.clone()),
+            Structured(ref fields) =>
+                Value::List(fields.iter().map(|&(ref id, ref t)| t.to_value(id)).collect()),
         }
     }
 
@@ -74,30 +205,68 @@ impl RecordDType {
     pub fn from_descr(descr: Value) -> Result<Self> {
         use RecordDType::*;
         match descr {
-            Value::String(string) => Ok(Simple(DType { ty: string, shape: vec![] })),
+            Value::String(string) => Ok(Simple(DType { ty: string, shape: vec![], nested: None })),
             Value::List(values) => Ok(Structured(from_list(values)?)),
-            _ => unimplemented!()
+            other => Err(malformed_descr(&format!("descr must be a string or a list, got {:?}", other))),
         }
     }
 }
 
+fn malformed_descr(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("malformed dtype descriptor: {}", msg))
+}
+
+fn parse_shape(value: &Value) -> Result<Vec<u64>> {
+    match *value {
+        Value::Integer(n) => Ok(vec![n as u64]),
+        Value::List(ref items) => items.iter().map(|item| match *item {
+            Value::Integer(n) => Ok(n as u64),
+            ref other => Err(malformed_descr(&format!("non-integer shape entry: {:?}", other))),
+        }).collect(),
+        ref other => Err(malformed_descr(&format!("shape must be an integer or a list of integers, got {:?}", other))),
+    }
+}
+
 fn from_list(values: Vec<Value>) -> Result<Vec<(String, DType)>> {
     let mut pairs = vec![];
     for value in values {
-        if let Value::List(field) = value {
-            pairs.push(convert_field(field)?);
-        } else {
-            unimplemented!()
+        match value {
+            Value::List(field) => pairs.push(convert_field(field)?),
+            other => return Err(malformed_descr(&format!("record field descriptor must be a list, got {:?}", other))),
         }
     }
     Ok(pairs)
 }
 
 fn convert_field(field: Vec<Value>) -> Result<(String, DType)> {
-    use self::Value::String;
-    match (&field[0], &field[1]) {
-        (&String(ref id), &String(ref t)) => Ok((id.clone(), DType { ty: t.clone(), shape: vec![] })),
-        _ => unimplemented!()
+    let mut parts = field.into_iter();
+
+    let name = match parts.next() {
+        Some(Value::String(id)) => id,
+        other => return Err(malformed_descr(&format!("field name must be a string, got {:?}", other))),
+    };
+
+    let ty_value = parts.next()
+        .ok_or_else(|| malformed_descr("field is missing a type"))?;
+
+    let shape = match parts.next() {
+        Some(ref shape_value) => parse_shape(shape_value)?,
+        None => vec![],
+    };
+
+    if parts.next().is_some() {
+        return Err(malformed_descr("field descriptor has more than three elements"));
+    }
+
+    match ty_value {
+        // A plain element type, e.g. `('a', '<i4')`.
+        Value::String(ty) => Ok((name, DType { ty, shape, nested: None })),
+        // A nested structured record, e.g. `('a', [('x', '<i4'), ('y', '<i4')])`.
+        Value::List(nested_fields) => {
+            let nested = RecordDType::Structured(from_list(nested_fields)?);
+            Ok((name, DType { ty: String::new(), shape, nested: Some(Box::new(nested)) }))
+        }
+        other => Err(malformed_descr(&format!("field type must be a string or a list, got {:?}", other))),
     }
 }
 
@@ -114,6 +283,111 @@ pub fn parse_header(bs: &[u8]) -> IResult<&[u8], Value> {
     parser::header(bs)
 }
 
+/// The three required top-level keys of an `.npy` header dictionary, validated and typed.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct HeaderDict {
+    pub descr: Value,
+    pub fortran_order: bool,
+    pub shape: Vec<u64>,
+}
+
+/// Parse an `.npy` header strictly.
+///
+/// Unlike `parse_header`, which collects the header dictionary into a `HashMap` and silently
+/// lets a later duplicate key clobber an earlier one, this rejects duplicate keys outright. It
+/// also requires the three keys `descr`, `fortran_order` and `shape` to all be present with the
+/// right value kinds, and rejects any unexpected extra key. Use this when a header that doesn't
+/// conform to NumPy's format should be treated as an error rather than silently accepted.
+pub fn parse_header_strict(bs: &[u8]) -> Result<HeaderDict> {
+    let pairs = match parser::header_strict(bs) {
+        IResult::Done(_, pairs) => pairs,
+        IResult::Error(_) => return Err(malformed_descr("could not parse header")),
+        IResult::Incomplete(_) => return Err(malformed_descr("truncated header")),
+    };
+
+    let mut seen = HashMap::new();
+    for &(ref key, _) in &pairs {
+        if seen.insert(key.clone(), ()).is_some() {
+            return Err(malformed_descr(&format!("duplicate header key: {:?}", key)));
+        }
+    }
+
+    const REQUIRED_KEYS: &[&str] = &["descr", "fortran_order", "shape"];
+    for &key in REQUIRED_KEYS {
+        if !seen.contains_key(key) {
+            return Err(malformed_descr(&format!("header is missing required key: {:?}", key)));
+        }
+    }
+    if seen.len() != REQUIRED_KEYS.len() {
+        let extra: Vec<_> = seen.keys().filter(|k| !REQUIRED_KEYS.contains(&k.as_str())).collect();
+        return Err(malformed_descr(&format!("header has unexpected keys: {:?}", extra)));
+    }
+
+    let mut dict: HashMap<String, Value> = pairs.into_iter().collect();
+
+    let descr = dict.remove("descr").unwrap();
+    match descr {
+        Value::String(_) | Value::List(_) => {}
+        ref other => return Err(malformed_descr(&format!("'descr' must be a string or a list, got {:?}", other))),
+    }
+
+    let fortran_order = match dict.remove("fortran_order").unwrap() {
+        Value::Bool(b) => b,
+        other => return Err(malformed_descr(&format!("'fortran_order' must be a bool, got {:?}", other))),
+    };
+
+    let shape = match dict.remove("shape").unwrap() {
+        Value::List(items) => items.into_iter().map(|item| match item {
+            Value::Integer(n) => Ok(n as u64),
+            other => Err(malformed_descr(&format!("'shape' entries must be integers, got {:?}", other))),
+        }).collect::<Result<Vec<u64>>>()?,
+        other => return Err(malformed_descr(&format!("'shape' must be a list, got {:?}", other))),
+    };
+
+    Ok(HeaderDict { descr, fortran_order, shape })
+}
+
+/// Build the full `.npy` header (magic, version, length and dictionary, padded to a multiple
+/// of 64 bytes and terminated by `\n`), choosing the smallest version that can hold `dict`.
+///
+/// Version 1.0 stores the header length as `le_u16`, which limits the padded dictionary (the
+/// part after the 10-byte magic/version/length preamble) to 65535 bytes. If `dict` doesn't fit,
+/// version 2.0 is used instead, which widens the length field to `le_u32`.
+pub fn format_header(dict: &str) -> Vec<u8> {
+    const ALIGN: usize = 64;
+
+    let build = |preamble_len: usize, len_field_size: usize| -> usize {
+        let unpadded = preamble_len + len_field_size + dict.len() + 1;
+        (unpadded + ALIGN - 1) / ALIGN * ALIGN
+    };
+
+    // preamble is "\x93NUMPY" (6 bytes) + 2 version bytes
+    let v1_total = build(8, 2);
+    let (major, minor, len_field_size, total_len) = if v1_total <= 65536 {
+        (1u8, 0u8, 2, v1_total)
+    } else {
+        (2u8, 0u8, 4, build(8, 4))
+    };
+
+    let mut header = Vec::with_capacity(total_len);
+    header.extend_from_slice(&[0x93u8]);
+    header.extend_from_slice(b"NUMPY");
+    header.push(major);
+    header.push(minor);
+
+    let dict_and_padding_len = total_len - 8 - len_field_size;
+    if len_field_size == 2 {
+        header.extend_from_slice(&(dict_and_padding_len as u16).to_le_bytes());
+    } else {
+        header.extend_from_slice(&(dict_and_padding_len as u32).to_le_bytes());
+    }
+
+    header.extend_from_slice(dict.as_bytes());
+    header.resize(total_len - 1, b' ');
+    header.push(b'\n');
+    header
+}
+
 mod parser {
     use super::Value;
     use nom::*;
@@ -122,12 +396,41 @@ mod parser {
         do_parse!(
             tag!(&[0x93u8]) >>
             tag!(b"NUMPY") >>
-            tag!(&[0x01u8, 0x00]) >>
-            hdr: length_value!(le_u16, item) >>
+            version: take!(2) >>
+            hdr: switch!(value!(version[0]),
+                1 => length_value!(le_u16, call!(dict_v1)) |
+                2 => length_value!(le_u32, call!(dict_v1)) |
+                _ => length_value!(le_u32, call!(dict_v3))
+            ) >>
             (hdr)
         )
     );
 
+    // Versions 1.0 and 2.0 encode the header dictionary as latin-1, where every byte maps
+    // directly to the codepoint of the same value, so it can be widened to UTF-8 losslessly.
+    fn dict_v1(bs: &[u8]) -> IResult<&[u8], Value> {
+        let text: String = bs.iter().map(|&b| b as char).collect();
+        item_from_owned(text)
+    }
+
+    // Version 3.0 encodes the header dictionary as UTF-8, allowing non-Latin-1 field names.
+    fn dict_v3(bs: &[u8]) -> IResult<&[u8], Value> {
+        match ::std::str::from_utf8(bs) {
+            Ok(text) => item_from_owned(text.to_string()),
+            Err(_) => IResult::Error(error_code!(ErrorKind::Custom(0))),
+        }
+    }
+
+    // `item` borrows from its input, so it can't be handed a temporary owned `String` directly;
+    // parse it and throw away the borrow, since the header dict is consumed in one shot anyway.
+    fn item_from_owned(text: String) -> IResult<&'static [u8], Value> {
+        match item(text.as_bytes()) {
+            IResult::Done(_, value) => IResult::Done(&[][..], value),
+            IResult::Error(e) => IResult::Error(e),
+            IResult::Incomplete(n) => IResult::Incomplete(n),
+        }
+    }
+
 
     named!(pub integer<Value>,
         map!(
@@ -197,6 +500,52 @@ mod parser {
             |v: Vec<_>| Value::Map(v.into_iter().collect())
         )
     );
+
+    // Like `map`, but keeps every key/value pair in order instead of collecting into a
+    // `HashMap`, so a caller can detect duplicate keys before they clobber each other.
+    named!(pub map_raw<Vec<(String, Value)>>,
+        ws!(
+            delimited!(tag!("{"),
+                terminated!(separated_list!(tag!(","),
+                    separated_pair!(map_opt!(string, |it| match it { Value::String(s) => Some(s), _ => None }), tag!(":"), item)
+                ), alt!(tag!(",") | tag!(""))),
+                tag!("}"))
+        )
+    );
+
+    named!(pub header_strict<Vec<(String, Value)>>,
+        do_parse!(
+            tag!(&[0x93u8]) >>
+            tag!(b"NUMPY") >>
+            version: take!(2) >>
+            hdr: switch!(value!(version[0]),
+                1 => length_value!(le_u16, call!(dict_v1_raw)) |
+                2 => length_value!(le_u32, call!(dict_v1_raw)) |
+                _ => length_value!(le_u32, call!(dict_v3_raw))
+            ) >>
+            (hdr)
+        )
+    );
+
+    fn dict_v1_raw(bs: &[u8]) -> IResult<&[u8], Vec<(String, Value)>> {
+        let text: String = bs.iter().map(|&b| b as char).collect();
+        map_raw_from_owned(text)
+    }
+
+    fn dict_v3_raw(bs: &[u8]) -> IResult<&[u8], Vec<(String, Value)>> {
+        match ::std::str::from_utf8(bs) {
+            Ok(text) => map_raw_from_owned(text.to_string()),
+            Err(_) => IResult::Error(error_code!(ErrorKind::Custom(0))),
+        }
+    }
+
+    fn map_raw_from_owned(text: String) -> IResult<&'static [u8], Vec<(String, Value)>> {
+        match map_raw(text.as_bytes()) {
+            IResult::Done(_, value) => IResult::Done(&[][..], value),
+            IResult::Error(e) => IResult::Error(e),
+            IResult::Incomplete(n) => IResult::Incomplete(n),
+        }
+    }
 }
 
 // #[test]
@@ -219,8 +568,8 @@ mod tests {
     #[test]
     fn description_of_record_array_as_python_list_of_tuples() {
         let dtype = RecordDType::Structured(vec![
-            ("float".to_string(), DType { ty: ">f4".to_string(), shape: vec![] }),
-            ("byte".to_string(), DType { ty: "<u1".to_string(), shape: vec![] }),
+            ("float".to_string(), DType { ty: ">f4".to_string(), shape: vec![], nested: None }),
+            ("byte".to_string(), DType { ty: "<u1".to_string(), shape: vec![], nested: None }),
         ]);
         let expected = "[('float', '>f4'), ('byte', '<u1'), ]";
         assert_eq!(dtype.descr(), expected);
@@ -228,7 +577,7 @@ mod tests {
 
     #[test]
     fn description_of_unstructured_primitive_array() {
-        let dtype = RecordDType::Simple(DType { ty: ">f8".to_string(), shape: vec![] });
+        let dtype = RecordDType::Simple(DType { ty: ">f8".to_string(), shape: vec![], nested: None });
         assert_eq!(dtype.descr(), "'>f8'");
     }
 
@@ -237,7 +586,7 @@ mod tests {
         let dtype = ">f8".to_string();
         assert_eq!(
             RecordDType::from_descr(Value::String(dtype.clone())).unwrap(),
-            RecordDType::Simple(DType { ty: dtype, shape: vec![] })
+            RecordDType::Simple(DType { ty: dtype, shape: vec![], nested: None })
         );
     }
 
@@ -245,9 +594,113 @@ mod tests {
     fn converts_record_description_to_record_dtype() {
         let descr = parser::item(b"[('a', '<u2'), ('b', '<f4')]").to_result().unwrap();
         let expected_dtype = RecordDType::Structured(vec![
-            ("a".to_string(), DType { ty: "<u2".to_string(), shape: vec![] }),
-            ("b".to_string(), DType { ty: "<f4".to_string(), shape: vec![] }),
+            ("a".to_string(), DType { ty: "<u2".to_string(), shape: vec![], nested: None }),
+            ("b".to_string(), DType { ty: "<f4".to_string(), shape: vec![], nested: None }),
         ]);
         assert_eq!(RecordDType::from_descr(descr).unwrap(), expected_dtype);
     }
+
+    #[test]
+    fn converts_subarray_field_to_record_dtype() {
+        let descr = parser::item(b"[('a', '<u2', (3, 4))]").to_result().unwrap();
+        let expected_dtype = RecordDType::Structured(vec![
+            ("a".to_string(), DType { ty: "<u2".to_string(), shape: vec![3, 4], nested: None }),
+        ]);
+        assert_eq!(RecordDType::from_descr(descr).unwrap(), expected_dtype);
+    }
+
+    #[test]
+    fn converts_nested_record_field_to_record_dtype() {
+        let descr = parser::item(b"[('a', [('x', '<i4'), ('y', '<i4')])]").to_result().unwrap();
+        let inner = RecordDType::Structured(vec![
+            ("x".to_string(), DType { ty: "<i4".to_string(), shape: vec![], nested: None }),
+            ("y".to_string(), DType { ty: "<i4".to_string(), shape: vec![], nested: None }),
+        ]);
+        let expected_dtype = RecordDType::Structured(vec![
+            ("a".to_string(), DType { ty: String::new(), shape: vec![], nested: Some(Box::new(inner)) }),
+        ]);
+        assert_eq!(RecordDType::from_descr(descr).unwrap(), expected_dtype);
+    }
+
+    #[test]
+    fn rejects_malformed_field_descriptor() {
+        let descr = parser::item(b"[('a', 5)]").to_result().unwrap();
+        assert!(RecordDType::from_descr(descr).is_err());
+    }
+
+    #[test]
+    fn parses_plain_numeric_type_strings() {
+        let dtype = DType { ty: "<i4".to_string(), shape: vec![], nested: None };
+        let descr = dtype.type_descr().unwrap();
+        assert_eq!(descr, TypeDescr { endianness: Endianness::Little, kind: TypeKind::Int, size: 4 });
+
+        let dtype = DType { ty: ">f8".to_string(), shape: vec![], nested: None };
+        let descr = dtype.type_descr().unwrap();
+        assert_eq!(descr, TypeDescr { endianness: Endianness::Big, kind: TypeKind::Float, size: 8 });
+    }
+
+    #[test]
+    fn parses_complex_type_string() {
+        let dtype = DType { ty: "<c16".to_string(), shape: vec![], nested: None };
+        let descr = dtype.type_descr().unwrap();
+        assert_eq!(descr, TypeDescr {
+            endianness: Endianness::Little,
+            kind: TypeKind::Complex { component_size: 8 },
+            size: 16,
+        });
+    }
+
+    #[test]
+    fn parses_unicode_type_string_as_four_bytes_per_char() {
+        let dtype = DType { ty: "<U4".to_string(), shape: vec![], nested: None };
+        let descr = dtype.type_descr().unwrap();
+        assert_eq!(descr, TypeDescr { endianness: Endianness::Little, kind: TypeKind::Unicode, size: 16 });
+    }
+
+    #[test]
+    fn parses_datetime_type_string_with_unit() {
+        let dtype = DType { ty: "<M8[ns]".to_string(), shape: vec![], nested: None };
+        let descr = dtype.type_descr().unwrap();
+        assert_eq!(descr, TypeDescr {
+            endianness: Endianness::Little,
+            kind: TypeKind::DateTime { unit: "ns".to_string() },
+            size: 8,
+        });
+    }
+
+    #[test]
+    fn rejects_unrecognized_type_code() {
+        let dtype = DType { ty: "<z4".to_string(), shape: vec![], nested: None };
+        assert!(dtype.type_descr().is_err());
+    }
+
+    #[test]
+    fn parse_header_strict_accepts_well_formed_header() {
+        let dict = "{'descr': '<i4', 'fortran_order': False, 'shape': (3, 4), }";
+        let bytes = format_header(dict);
+        let parsed = parse_header_strict(&bytes).unwrap();
+        assert_eq!(parsed.fortran_order, false);
+        assert_eq!(parsed.shape, vec![3, 4]);
+    }
+
+    #[test]
+    fn parse_header_strict_rejects_duplicate_keys() {
+        let dict = "{'descr': '<i4', 'descr': '<f8', 'fortran_order': False, 'shape': (3,), }";
+        let bytes = format_header(dict);
+        assert!(parse_header_strict(&bytes).is_err());
+    }
+
+    #[test]
+    fn parse_header_strict_rejects_missing_required_key() {
+        let dict = "{'descr': '<i4', 'fortran_order': False, }";
+        let bytes = format_header(dict);
+        assert!(parse_header_strict(&bytes).is_err());
+    }
+
+    #[test]
+    fn parse_header_strict_rejects_unexpected_key() {
+        let dict = "{'descr': '<i4', 'fortran_order': False, 'shape': (3,), 'extra': True, }";
+        let bytes = format_header(dict);
+        assert!(parse_header_strict(&bytes).is_err());
+    }
 }
@@ -0,0 +1,137 @@
+//! Reading and writing `.npz` archives: zip files that package several named `.npy` streams,
+//! as produced by `numpy.savez`/`numpy.savez_compressed` and read back by `numpy.load`.
+//!
+//! This module is layered entirely on top of [`crate::header`] (for decoding/encoding the
+//! per-member `.npy` header) and the `zip` crate (for the archive container itself). It does
+//! not depend on a typed array reader/writer, since this tree doesn't have one: [`NpzArchive`]
+//! hands back a member's decoded header plus a `Read` over its raw element bytes, and
+//! [`NpzWriter`] accepts a member's header plus a `Write` to stream its raw element bytes into,
+//! leaving endian/element (de)serialization to whatever layer the caller has for that.
+
+use std::io::{Read, Result, Write};
+
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use zip::write::FileOptions;
+
+use crate::header::{self, HeaderDict, RecordDType};
+
+/// A decoded `.npy` header: the record's dtype, its shape, and whether it's stored in
+/// Fortran (column-major) order.
+pub struct ArrayHeader {
+    pub dtype: RecordDType,
+    pub shape: Vec<u64>,
+    pub fortran_order: bool,
+}
+
+/// A `.npz` archive opened for reading, as a lazily-decoded map from member name to array.
+pub struct NpzArchive<R: Read + std::io::Seek> {
+    zip: ZipArchive<R>,
+}
+
+impl<R: Read + std::io::Seek> NpzArchive<R> {
+    /// Open a `.npz` archive. Member headers are not decoded until [`NpzArchive::by_name`] is
+    /// called, so opening a large archive is cheap.
+    pub fn new(reader: R) -> Result<Self> {
+        let zip = ZipArchive::new(reader).map_err(zip_err_to_io)?;
+        Ok(NpzArchive { zip })
+    }
+
+    /// Names of every array stored in the archive, without the `.npy` extension
+    /// `numpy.savez` adds to each member.
+    pub fn array_names(&self) -> impl Iterator<Item = &str> {
+        self.zip.file_names().map(|name| name.trim_end_matches(".npy"))
+    }
+
+    /// Decode the header of the named array and return it along with a reader positioned at
+    /// the start of its raw, still-encoded element data.
+    pub fn by_name(&mut self, name: &str) -> Result<(ArrayHeader, impl Read + '_)> {
+        let member_name = format!("{}.npy", name);
+        let mut file = self.zip.by_name(&member_name).map_err(zip_err_to_io)?;
+
+        // The header's length isn't known up front, so its bytes are read incrementally:
+        // magic + version (8 bytes), then the length field, then exactly that many more bytes.
+        let mut preamble = [0u8; 8];
+        file.read_exact(&mut preamble)?;
+        let len_field_size = if preamble[6] == 1 { 2 } else { 4 };
+        let mut len_bytes = [0u8; 4];
+        file.read_exact(&mut len_bytes[..len_field_size])?;
+        let dict_len = if len_field_size == 2 {
+            u16::from_le_bytes([len_bytes[0], len_bytes[1]]) as usize
+        } else {
+            u32::from_le_bytes(len_bytes) as usize
+        };
+
+        let mut rest = Vec::with_capacity(8 + len_field_size + dict_len);
+        rest.extend_from_slice(&preamble);
+        rest.extend_from_slice(&len_bytes[..len_field_size]);
+        rest.resize(rest.len() + dict_len, 0);
+        let dict_start = 8 + len_field_size;
+        file.read_exact(&mut rest[dict_start..])?;
+
+        let dict = header::parse_header_strict(&rest)?;
+        let HeaderDict { descr, fortran_order, shape } = dict;
+        let dtype = RecordDType::from_descr(descr)?;
+
+        Ok((ArrayHeader { dtype, shape, fortran_order }, file))
+    }
+}
+
+fn zip_err_to_io(e: zip::result::ZipError) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+}
+
+/// Whether array members of a `.npz` archive are compressed, matching the choice between
+/// `numpy.savez` (uncompressed) and `numpy.savez_compressed` (deflate).
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum NpzCompression {
+    Stored,
+    Deflated,
+}
+
+/// Builds a `.npz` archive one array at a time, so large arrays don't all need to be held in
+/// memory at once: each array's raw element bytes are streamed directly into the zip entry via
+/// the `Write` returned by [`NpzWriter::start_array`].
+pub struct NpzWriter<W: Write + std::io::Seek> {
+    zip: ZipWriter<W>,
+    compression: NpzCompression,
+}
+
+impl<W: Write + std::io::Seek> NpzWriter<W> {
+    pub fn new(writer: W, compression: NpzCompression) -> Self {
+        NpzWriter { zip: ZipWriter::new(writer), compression }
+    }
+
+    /// Start a new array member: writes its `.npy` header immediately, and returns a `Write`
+    /// that streams the array's raw, already-encoded element bytes into the archive.
+    pub fn start_array(
+        &mut self,
+        name: &str,
+        dtype: &RecordDType,
+        shape: &[u64],
+        fortran_order: bool,
+    ) -> Result<&mut dyn Write> {
+        let method = match self.compression {
+            NpzCompression::Stored => CompressionMethod::Stored,
+            NpzCompression::Deflated => CompressionMethod::Deflated,
+        };
+        let options = FileOptions::default().compression_method(method);
+
+        self.zip.start_file(format!("{}.npy", name), options).map_err(zip_err_to_io)?;
+
+        let shape_str = shape.iter().fold(String::new(), |o, n| o + &format!("{},", n));
+        let dict = format!(
+            "{{'descr': {}, 'fortran_order': {}, 'shape': ({}), }}",
+            dtype.descr(),
+            if fortran_order { "True" } else { "False" },
+            shape_str,
+        );
+        self.zip.write_all(&header::format_header(&dict))?;
+
+        Ok(&mut self.zip)
+    }
+
+    /// Finish the archive, flushing the central directory.
+    pub fn finish(mut self) -> Result<W> {
+        self.zip.finish().map_err(zip_err_to_io)
+    }
+}